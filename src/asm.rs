@@ -0,0 +1,410 @@
+//! A two-pass assembler for the mnemonic syntax produced by `Instruction`'s
+//! `Display` impl (e.g. `LD V0, 12`, `DRW V1, V2, 5`, `JP 200`).
+//!
+//! Numeric literals (constants, addresses, `db` bytes) are parsed as
+//! hexadecimal, matching how the disassembler prints them, so that
+//! `assemble(&format!("{}", instruction))` round-trips.
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct AsmError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+struct Patch {
+    offset: usize,
+    label: String,
+    opcode_nibble: u8,
+    line: usize,
+}
+
+fn err(line: usize, message: impl Into<String>) -> AsmError {
+    AsmError {
+        line,
+        message: message.into(),
+    }
+}
+
+fn parse_number(token: &str, line: usize) -> Result<u16, AsmError> {
+    let token = token.trim();
+    let digits = token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+        .unwrap_or(token);
+    u16::from_str_radix(digits, 16).map_err(|_| err(line, format!("invalid number '{}'", token)))
+}
+
+fn parse_register(token: &str, line: usize) -> Result<u8, AsmError> {
+    let token = token.trim();
+    if token.len() < 2 || !(token.starts_with('V') || token.starts_with('v')) {
+        return Err(err(line, format!("expected register, found '{}'", token)));
+    }
+    u8::from_str_radix(&token[1..], 16).map_err(|_| err(line, format!("invalid register '{}'", token)))
+}
+
+fn is_register(token: &str) -> bool {
+    let token = token.trim();
+    token.len() >= 2
+        && (token.starts_with('V') || token.starts_with('v'))
+        && u8::from_str_radix(&token[1..], 16).is_ok()
+}
+
+/// Resolves an address operand, deferring to a backpatch if it is a label
+/// rather than a literal number.
+fn emit_address(
+    bytes: &mut Vec<u8>,
+    patches: &mut Vec<Patch>,
+    opcode_nibble: u8,
+    operand: &str,
+    line: usize,
+) -> Result<(), AsmError> {
+    let offset = bytes.len();
+    let operand = operand.trim();
+    match parse_number(operand, line) {
+        Ok(addr) => {
+            if addr > 0xfff {
+                return Err(err(line, format!("address {:#X} out of range (> 0xFFF)", addr)));
+            }
+            bytes.push((opcode_nibble << 4) | ((addr >> 8) as u8));
+            bytes.push((addr & 0xff) as u8);
+        }
+        Err(_) => {
+            patches.push(Patch {
+                offset,
+                label: operand.to_string(),
+                opcode_nibble,
+                line,
+            });
+            bytes.push(opcode_nibble << 4);
+            bytes.push(0);
+        }
+    }
+    Ok(())
+}
+
+/// Minimum operand count each mnemonic needs before its handler is safe to
+/// index into `operands`. Unknown mnemonics are rejected later, in the match.
+fn min_operands(mnemonic: &str) -> usize {
+    match mnemonic {
+        "CLS" | "RET" | "DB" => 0,
+        "SYS" | "JP" | "CALL" | "SKP" | "SKNP" => 1,
+        "SE" | "SNE" | "OR" | "AND" | "XOR" | "SUB" | "SHR" | "SUBN" | "SHL" | "ADD" | "RND"
+        | "LD" => 2,
+        "DRW" => 3,
+        _ => 0,
+    }
+}
+
+fn emit_constant(bytes: &mut Vec<u8>, hi: u8, reg: u8, token: &str, line: usize) -> Result<(), AsmError> {
+    let value = parse_number(token, line)?;
+    if value > 0xff {
+        return Err(err(line, format!("constant {:#X} out of range (> 0xFF)", value)));
+    }
+    bytes.push((hi << 4) | reg);
+    bytes.push(value as u8);
+    Ok(())
+}
+
+pub fn assemble(src: &str) -> Result<Vec<u8>, AsmError> {
+    let mut org: usize = 0x200;
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut patches: Vec<Patch> = Vec::new();
+    let mut bytes: Vec<u8> = Vec::new();
+
+    for (i, raw_line) in src.lines().enumerate() {
+        let line_no = i + 1;
+        let line = match raw_line.split(';').next() {
+            Some(l) => l.trim(),
+            None => "",
+        };
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(label) = line.strip_suffix(':') {
+            labels.insert(label.trim().to_string(), org + bytes.len());
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix(".org") {
+            let addr = parse_number(rest.trim(), line_no)?;
+            org = addr as usize - bytes.len();
+            continue;
+        }
+
+        let (mnemonic, rest) = match line.split_once(char::is_whitespace) {
+            Some((m, r)) => (m, r.trim()),
+            None => (line, ""),
+        };
+        let operands: Vec<&str> = if rest.is_empty() {
+            Vec::new()
+        } else {
+            rest.split(',').map(|s| s.trim()).collect()
+        };
+        let mnemonic = mnemonic.to_uppercase();
+
+        let required = min_operands(&mnemonic);
+        if operands.len() < required {
+            return Err(err(
+                line_no,
+                format!(
+                    "{} expects at least {} operand(s), found {}",
+                    mnemonic,
+                    required,
+                    operands.len()
+                ),
+            ));
+        }
+
+        match mnemonic.as_str() {
+            "DB" => {
+                for token in &operands {
+                    let value = parse_number(token, line_no)?;
+                    if value > 0xff {
+                        return Err(err(line_no, format!("byte {:#X} out of range (> 0xFF)", value)));
+                    }
+                    bytes.push(value as u8);
+                }
+            }
+            "CLS" => bytes.extend_from_slice(&[0x00, 0xe0]),
+            "RET" => bytes.extend_from_slice(&[0x00, 0xee]),
+            "SYS" => emit_address(&mut bytes, &mut patches, 0x0, operands[0], line_no)?,
+            "JP" if operands.len() == 2 => {
+                emit_address(&mut bytes, &mut patches, 0xb, operands[1], line_no)?
+            }
+            "JP" => emit_address(&mut bytes, &mut patches, 0x1, operands[0], line_no)?,
+            "CALL" => emit_address(&mut bytes, &mut patches, 0x2, operands[0], line_no)?,
+            "SE" if is_register(operands[1]) => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x50 | x, y << 4]);
+            }
+            "SE" => {
+                let x = parse_register(operands[0], line_no)?;
+                emit_constant(&mut bytes, 0x3, x, operands[1], line_no)?
+            }
+            "SNE" if is_register(operands[1]) => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x90 | x, y << 4]);
+            }
+            "SNE" => {
+                let x = parse_register(operands[0], line_no)?;
+                emit_constant(&mut bytes, 0x4, x, operands[1], line_no)?
+            }
+            "OR" => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x80 | x, (y << 4) | 0x1]);
+            }
+            "AND" => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x80 | x, (y << 4) | 0x2]);
+            }
+            "XOR" => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x80 | x, (y << 4) | 0x3]);
+            }
+            "SUB" => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x80 | x, (y << 4) | 0x5]);
+            }
+            "SHR" => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x80 | x, (y << 4) | 0x6]);
+            }
+            "SUBN" => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x80 | x, (y << 4) | 0x7]);
+            }
+            "SHL" => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x80 | x, (y << 4) | 0xe]);
+            }
+            "RND" => {
+                let x = parse_register(operands[0], line_no)?;
+                emit_constant(&mut bytes, 0xc, x, operands[1], line_no)?
+            }
+            "DRW" => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                let n = parse_number(operands[2], line_no)?;
+                if n > 0xf {
+                    return Err(err(line_no, format!("sprite height {:#X} out of range (> 0xF)", n)));
+                }
+                bytes.extend_from_slice(&[0xd0 | x, (y << 4) | n as u8]);
+            }
+            "SKP" => {
+                let x = parse_register(operands[0], line_no)?;
+                bytes.extend_from_slice(&[0xe0 | x, 0x9e]);
+            }
+            "SKNP" => {
+                let x = parse_register(operands[0], line_no)?;
+                bytes.extend_from_slice(&[0xe0 | x, 0xa1]);
+            }
+            "ADD" if operands[0].eq_ignore_ascii_case("I") => {
+                let x = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0xf0 | x, 0x1e]);
+            }
+            "ADD" if is_register(operands[1]) => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x80 | x, (y << 4) | 0x4]);
+            }
+            "ADD" => {
+                let x = parse_register(operands[0], line_no)?;
+                emit_constant(&mut bytes, 0x7, x, operands[1], line_no)?
+            }
+            "LD" if operands[0].eq_ignore_ascii_case("I") => {
+                emit_address(&mut bytes, &mut patches, 0xa, operands[1], line_no)?
+            }
+            "LD" if operands[0].eq_ignore_ascii_case("DT") => {
+                let x = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0xf0 | x, 0x15]);
+            }
+            "LD" if operands[0].eq_ignore_ascii_case("ST") => {
+                let x = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0xf0 | x, 0x18]);
+            }
+            "LD" if operands[0].eq_ignore_ascii_case("F") => {
+                let x = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0xf0 | x, 0x29]);
+            }
+            "LD" if operands[0].eq_ignore_ascii_case("B") => {
+                let x = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0xf0 | x, 0x33]);
+            }
+            "LD" if operands[0].eq_ignore_ascii_case("[I]") => {
+                let x = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0xf0 | x, 0x55]);
+            }
+            "LD" if operands[1].eq_ignore_ascii_case("DT") => {
+                let x = parse_register(operands[0], line_no)?;
+                bytes.extend_from_slice(&[0xf0 | x, 0x07]);
+            }
+            "LD" if operands[1].eq_ignore_ascii_case("K") => {
+                let x = parse_register(operands[0], line_no)?;
+                bytes.extend_from_slice(&[0xf0 | x, 0x0a]);
+            }
+            "LD" if operands[1].eq_ignore_ascii_case("[I]") => {
+                let x = parse_register(operands[0], line_no)?;
+                bytes.extend_from_slice(&[0xf0 | x, 0x65]);
+            }
+            "LD" if is_register(operands[1]) => {
+                let x = parse_register(operands[0], line_no)?;
+                let y = parse_register(operands[1], line_no)?;
+                bytes.extend_from_slice(&[0x80 | x, y << 4]);
+            }
+            "LD" => {
+                let x = parse_register(operands[0], line_no)?;
+                emit_constant(&mut bytes, 0x6, x, operands[1], line_no)?
+            }
+            other => return Err(err(line_no, format!("unknown mnemonic '{}'", other))),
+        }
+    }
+
+    for patch in &patches {
+        let addr = *labels
+            .get(&patch.label)
+            .ok_or_else(|| err(patch.line, format!("undeclared label '{}'", patch.label)))?;
+        if addr > 0xfff {
+            return Err(err(
+                patch.line,
+                format!("address {:#X} out of range (> 0xFFF)", addr),
+            ));
+        }
+        bytes[patch.offset] = (patch.opcode_nibble << 4) | ((addr >> 8) as u8);
+        bytes[patch.offset + 1] = (addr & 0xff) as u8;
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assembles_simple_instructions() {
+        assert_eq!(assemble("CLS").unwrap(), vec![0x00, 0xe0]);
+        assert_eq!(assemble("RET").unwrap(), vec![0x00, 0xee]);
+        assert_eq!(assemble("LD V0, 12").unwrap(), vec![0x60, 0x12]);
+        assert_eq!(assemble("ADD I, V3").unwrap(), vec![0xf3, 0x1e]);
+        assert_eq!(assemble("DRW V1, V2, 5").unwrap(), vec![0xd1, 0x25]);
+    }
+
+    #[test]
+    fn round_trips_through_the_disassembler() {
+        for bytes in [
+            vec![0x60, 0x12],
+            vec![0xf3, 0x1e],
+            vec![0xd1, 0x25],
+            vec![0xf2, 0x55],
+            vec![0xf2, 0x65],
+            vec![0xa3, 0x00],
+        ] {
+            let text = crate::disassemble(0, &bytes);
+            let reassembled = assemble(&text).unwrap();
+            assert_eq!(reassembled, bytes, "round-trip of '{}' failed", text);
+        }
+    }
+
+    #[test]
+    fn backpatches_a_forward_label_reference() {
+        let bytes = assemble("JP end\nCLS\nend:\nRET").unwrap();
+        assert_eq!(bytes, vec![0x12, 0x04, 0x00, 0xe0, 0x00, 0xee]);
+    }
+
+    #[test]
+    fn backpatches_a_backward_label_reference() {
+        let bytes = assemble("start:\nCLS\nJP start").unwrap();
+        assert_eq!(bytes, vec![0x00, 0xe0, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn rejects_unknown_mnemonic() {
+        let err = assemble("FOO V0, V1").unwrap_err();
+        assert!(err.to_string().contains("unknown mnemonic"));
+    }
+
+    #[test]
+    fn rejects_out_of_range_constant() {
+        let err = assemble("LD V0, 256").unwrap_err();
+        assert!(err.to_string().contains("out of range"));
+    }
+
+    #[test]
+    fn rejects_out_of_range_address() {
+        let err = assemble("JP FFF0").unwrap_err();
+        assert!(err.to_string().contains("out of range"));
+    }
+
+    #[test]
+    fn rejects_undeclared_label() {
+        let err = assemble("JP nowhere").unwrap_err();
+        assert!(err.to_string().contains("undeclared label"));
+    }
+
+    #[test]
+    fn rejects_insufficient_operands() {
+        let err = assemble("LD V0").unwrap_err();
+        assert!(err.to_string().contains("expects at least"));
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+
+use crate::asm;
+use crate::{disassemble, Display, Keys, MachineState, NullAudioOutput, Quirks};
+
+const INSTRUCTION_SIZE: usize = 2;
+
+/// An interactive debugger wrapping the CPU run loop: breakpoints,
+/// single-stepping, register/memory inspection, and on-the-fly patching
+/// via the assembler. Repeating the last command on an empty line and
+/// `step <n>` both mirror a typical machine-monitor prompt.
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    last_command: Option<String>,
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            last_command: None,
+        }
+    }
+
+    pub fn set_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn clear_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.remove(&addr);
+    }
+
+    pub fn has_breakpoint(&self, addr: usize) -> bool {
+        self.breakpoints.contains(&addr)
+    }
+
+    /// Runs the prompt loop until the user continues, quits, or `input` is
+    /// exhausted. Returns nothing in any of these cases, so the caller is
+    /// not told whether to resume execution or stop.
+    #[allow(clippy::too_many_arguments)]
+    pub fn run<R: BufRead, W: Write>(
+        &mut self,
+        state: &mut MachineState,
+        memory: &mut [u8],
+        display: &mut Display,
+        keys: &Keys,
+        quirks: &Quirks,
+        input: &mut R,
+        output: &mut W,
+    ) {
+        loop {
+            if self.breakpoints.contains(&state.ip()) {
+                writeln!(output, "breakpoint hit at {:03X}", state.ip()).ok();
+            }
+
+            write!(output, "(rip8) ").ok();
+            output.flush().ok();
+
+            let mut line = String::new();
+            if input.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let line = line.trim();
+            let command = if line.is_empty() {
+                match self.last_command.clone() {
+                    Some(c) => c,
+                    None => continue,
+                }
+            } else {
+                line.to_string()
+            };
+            self.last_command = Some(command.clone());
+
+            match self.execute_command(&command, state, memory, display, keys, quirks, output) {
+                Action::Continue => continue,
+                Action::Resume => return,
+                Action::Quit => return,
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn execute_command<W: Write>(
+        &mut self,
+        command: &str,
+        state: &mut MachineState,
+        memory: &mut [u8],
+        display: &mut Display,
+        keys: &Keys,
+        quirks: &Quirks,
+        output: &mut W,
+    ) -> Action {
+        let mut parts = command.split_whitespace();
+        let cmd = parts.next().unwrap_or("");
+        let rest: Vec<&str> = parts.collect();
+
+        match cmd {
+            "step" | "s" => {
+                let repeat = rest.first().and_then(|n| n.parse().ok()).unwrap_or(1);
+                for _ in 0..repeat {
+                    state.execute(memory, display, keys, quirks, &mut NullAudioOutput);
+                }
+                Action::Continue
+            }
+            "continue" | "c" => Action::Resume,
+            "break" | "b" => {
+                if let Some(addr) = parse_addr(rest.first()) {
+                    self.set_breakpoint(addr);
+                    writeln!(output, "breakpoint set at {:03X}", addr).ok();
+                }
+                Action::Continue
+            }
+            "clear" => {
+                if let Some(addr) = parse_addr(rest.first()) {
+                    self.clear_breakpoint(addr);
+                    writeln!(output, "breakpoint cleared at {:03X}", addr).ok();
+                }
+                Action::Continue
+            }
+            "regs" | "r" => {
+                for i in 0..16 {
+                    write!(output, "V{:X}={:02X} ", i, state.register(i)).ok();
+                }
+                writeln!(
+                    output,
+                    "\nI={:03X} SP={:X} PC={:03X} DT={:02X} ST={:02X}",
+                    state.address(),
+                    state.sp(),
+                    state.ip(),
+                    state.delay(),
+                    state.sound()
+                )
+                .ok();
+                Action::Continue
+            }
+            "mem" | "x" => {
+                let addr = parse_addr(rest.first()).unwrap_or_else(|| state.ip());
+                let len = rest.get(1).and_then(|n| n.parse().ok()).unwrap_or(16);
+                hexdump(memory, addr, len, output);
+                Action::Continue
+            }
+            "dis" | "d" => {
+                let mut addr = parse_addr(rest.first()).unwrap_or_else(|| state.ip());
+                let count = rest.get(1).and_then(|n| n.parse().ok()).unwrap_or(5);
+                for _ in 0..count {
+                    if addr + 1 >= memory.len() {
+                        break;
+                    }
+                    writeln!(output, "{:03X}: {}", addr, disassemble(addr, memory)).ok();
+                    addr += INSTRUCTION_SIZE;
+                }
+                Action::Continue
+            }
+            "asm" => {
+                if let Some(addr) = parse_addr(rest.first()) {
+                    let source = command
+                        .splitn(3, char::is_whitespace)
+                        .nth(2)
+                        .unwrap_or("");
+                    match asm::assemble(source) {
+                        Ok(bytes) if addr + bytes.len() <= memory.len() => {
+                            memory[addr..addr + bytes.len()].copy_from_slice(&bytes);
+                            writeln!(output, "patched {:03X}: {}", addr, source).ok();
+                        }
+                        Ok(bytes) => {
+                            writeln!(
+                                output,
+                                "{:03X}..{:03X} out of range (memory is {:03X} bytes)",
+                                addr,
+                                addr + bytes.len(),
+                                memory.len()
+                            )
+                            .ok();
+                        }
+                        Err(e) => {
+                            writeln!(output, "asm error: {}", e).ok();
+                        }
+                    }
+                }
+                Action::Continue
+            }
+            "quit" | "q" => Action::Quit,
+            _ => {
+                writeln!(output, "unknown command: {}", cmd).ok();
+                Action::Continue
+            }
+        }
+    }
+}
+
+enum Action {
+    Continue,
+    Resume,
+    Quit,
+}
+
+fn parse_addr(token: Option<&&str>) -> Option<usize> {
+    let token = token?;
+    let digits = token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+        .unwrap_or(token);
+    usize::from_str_radix(digits, 16).ok()
+}
+
+fn hexdump<W: Write>(memory: &[u8], addr: usize, len: usize, output: &mut W) {
+    if addr >= memory.len() {
+        writeln!(
+            output,
+            "{:03X} out of range (memory is {:03X} bytes)",
+            addr,
+            memory.len()
+        )
+        .ok();
+        return;
+    }
+    let end = (addr + len).min(memory.len());
+    for (row, chunk) in memory[addr..end].chunks(16).enumerate() {
+        write!(output, "{:03X}: ", addr + row * 16).ok();
+        for byte in chunk {
+            write!(output, "{:02X} ", byte).ok();
+        }
+        writeln!(output).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Display, Keys, MachineState, Quirks};
+    use std::io::Cursor;
+
+    fn new_session() -> (Debugger, MachineState, Vec<u8>) {
+        (Debugger::new(), MachineState::new(), vec![0u8; 0x1000])
+    }
+
+    fn drive(debugger: &mut Debugger, state: &mut MachineState, memory: &mut [u8], input: &str) -> String {
+        let mut display = Display::new();
+        let keys = Keys::new();
+        let quirks = Quirks::chip8();
+        let mut output = Vec::new();
+        debugger.run(
+            state,
+            memory,
+            &mut display,
+            &keys,
+            &quirks,
+            &mut Cursor::new(input.as_bytes()),
+            &mut output,
+        );
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn reports_breakpoint_hit_at_the_current_ip() {
+        let (mut debugger, mut state, mut memory) = new_session();
+        debugger.set_breakpoint(0x200);
+        let output = drive(&mut debugger, &mut state, &mut memory, "quit\n");
+        assert!(output.contains("breakpoint hit at 200"));
+    }
+
+    #[test]
+    fn clearing_a_breakpoint_stops_it_firing() {
+        let mut debugger = Debugger::new();
+        debugger.set_breakpoint(0x200);
+        assert!(debugger.has_breakpoint(0x200));
+        debugger.clear_breakpoint(0x200);
+        assert!(!debugger.has_breakpoint(0x200));
+    }
+
+    #[test]
+    fn step_n_executes_n_instructions() {
+        let (mut debugger, mut state, mut memory) = new_session();
+        memory[0x200] = 0x60; // LD V0, 1
+        memory[0x201] = 0x01;
+        memory[0x202] = 0x70; // ADD V0, 1
+        memory[0x203] = 0x01;
+        drive(&mut debugger, &mut state, &mut memory, "step 2\nquit\n");
+        assert_eq!(state.register(0), 2);
+        assert_eq!(state.ip(), 0x204);
+    }
+
+    #[test]
+    fn repeating_the_last_command_on_a_blank_line_steps_again() {
+        let (mut debugger, mut state, mut memory) = new_session();
+        memory[0x200] = 0x60; // LD V0, 1
+        memory[0x201] = 0x01;
+        memory[0x202] = 0x70; // ADD V0, 1
+        memory[0x203] = 0x01;
+        drive(&mut debugger, &mut state, &mut memory, "step\n\nquit\n");
+        assert_eq!(state.register(0), 2);
+    }
+
+    #[test]
+    fn asm_command_patches_memory_in_place() {
+        let (mut debugger, mut state, mut memory) = new_session();
+        let output = drive(
+            &mut debugger,
+            &mut state,
+            &mut memory,
+            "asm 200 LD V0, 5\nstep\nquit\n",
+        );
+        assert!(output.contains("patched 200"));
+        assert_eq!(state.register(0), 5);
+    }
+
+    #[test]
+    fn asm_command_reports_errors_instead_of_patching() {
+        let (mut debugger, mut state, mut memory) = new_session();
+        let output = drive(&mut debugger, &mut state, &mut memory, "asm 200 FOO\nquit\n");
+        assert!(output.contains("asm error"));
+    }
+
+    #[test]
+    fn mem_command_hexdumps_the_requested_range() {
+        let (mut debugger, mut state, mut memory) = new_session();
+        memory[0x200] = 0xab;
+        memory[0x201] = 0xcd;
+        let output = drive(&mut debugger, &mut state, &mut memory, "mem 200 2\nquit\n");
+        assert!(output.contains("200: AB CD"));
+    }
+
+    #[test]
+    fn regs_command_prints_register_and_ip_state() {
+        let (mut debugger, mut state, mut memory) = new_session();
+        let output = drive(&mut debugger, &mut state, &mut memory, "regs\nquit\n");
+        assert!(output.contains("PC=200"));
+    }
+}
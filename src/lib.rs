@@ -1,5 +1,11 @@
 use std::fmt;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+pub mod asm;
+pub mod debugger;
+pub mod quirks;
+
+pub use quirks::Quirks;
 
 #[derive(Copy, Clone)]
 struct Address {
@@ -167,31 +173,361 @@ impl fmt::Display for Instruction {
             Instruction::StoreBCD(x) => write!(f, "LD B, {}", x),
             Instruction::StoreRegisters(x) => write!(f, "LD [I], {}", x),
             Instruction::LoadRegisters(x) => write!(f, "LD {}, [I]", x),
-            _ => unimplemented!(),
         }
     }
 }
 
-struct MachineState {
+const NUM_KEYS: usize = 16;
+const STACK_SIZE: usize = 16;
+const DISPLAY_WIDTH: usize = 64;
+const DISPLAY_HEIGHT: usize = 32;
+const FONT_SPRITE_SIZE: u16 = 5;
+
+pub struct Keys {
+    pressed: [bool; NUM_KEYS],
+}
+
+impl Default for Keys {
+    fn default() -> Keys {
+        Keys::new()
+    }
+}
+
+impl Keys {
+    pub fn new() -> Keys {
+        Keys {
+            pressed: [false; NUM_KEYS],
+        }
+    }
+
+    pub fn set_pressed(&mut self, key: u8, pressed: bool) {
+        self.pressed[(key & 0xf) as usize] = pressed;
+    }
+
+    /// Keys are nibble-addressed (0-F); a ROM-controlled `Vx` can carry any
+    /// byte, so the low nibble is what's actually looked up.
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.pressed[(key & 0xf) as usize]
+    }
+
+    fn first_pressed(&self) -> Option<u8> {
+        self.pressed.iter().position(|&p| p).map(|k| k as u8)
+    }
+}
+
+pub struct Display {
+    pixels: [[bool; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+}
+
+impl Default for Display {
+    fn default() -> Display {
+        Display::new()
+    }
+}
+
+impl Display {
+    pub fn new() -> Display {
+        Display {
+            pixels: [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels = [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.pixels[y % DISPLAY_HEIGHT][x % DISPLAY_WIDTH]
+    }
+
+    fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
+        let mut collision = false;
+        for (row, byte) in sprite.iter().enumerate() {
+            let py = (y as usize + row) % DISPLAY_HEIGHT;
+            for bit in 0..8 {
+                if byte & (0x80 >> bit) == 0 {
+                    continue;
+                }
+                let px = (x as usize + bit) % DISPLAY_WIDTH;
+                let pixel = &mut self.pixels[py][px];
+                if *pixel {
+                    collision = true;
+                }
+                *pixel ^= true;
+            }
+        }
+        collision
+    }
+}
+
+pub struct MachineState {
     ip: usize,
     sp: usize,
     finished: bool,
     addr: Address,
     registers: [u8; NUM_REGISTERS],
+    stack: [usize; STACK_SIZE],
+    timers: Timers,
+}
+
+impl Default for MachineState {
+    fn default() -> MachineState {
+        MachineState::new()
+    }
 }
 
 impl MachineState {
-    fn new() -> MachineState {
+    pub fn new() -> MachineState {
         MachineState {
             ip: 0x200,
             sp: 0,
             finished: false,
             addr: Address { value: 0 },
             registers: [0; NUM_REGISTERS],
+            stack: [0; STACK_SIZE],
+            timers: Timers::new(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn sp(&self) -> usize {
+        self.sp
+    }
+
+    pub fn address(&self) -> u16 {
+        self.addr.value
+    }
+
+    pub fn register(&self, r: u8) -> u8 {
+        self.registers[r as usize]
+    }
+
+    pub fn set_register(&mut self, r: u8, value: u8) {
+        self.registers[r as usize] = value;
+    }
+
+    pub fn delay(&self) -> u16 {
+        self.timers.delay
+    }
+
+    pub fn sound(&self) -> u16 {
+        self.timers.sound
+    }
+
+    pub fn is_sound_active(&self) -> bool {
+        self.timers.is_sound_active()
+    }
+
+    pub fn execute(
+        &mut self,
+        memory: &mut [u8],
+        display: &mut Display,
+        keys: &Keys,
+        quirks: &Quirks,
+        audio: &mut dyn AudioOutput,
+    ) {
+        self.timers.update();
+        audio.set_playing(self.timers.is_sound_active());
+        let instruction = decode_instruction(self.ip, memory);
+        let mut next_ip = self.ip + 2;
+
+        match instruction {
+            Instruction::SysCall(_) => {}
+            Instruction::ClearScreen => display.clear(),
+            Instruction::Return => {
+                self.sp -= 1;
+                next_ip = self.stack[self.sp];
+            }
+            Instruction::Jump(addr) => next_ip = addr.value as usize,
+            Instruction::Call(addr) => {
+                self.stack[self.sp] = next_ip;
+                self.sp += 1;
+                next_ip = addr.value as usize;
+            }
+            Instruction::SkipIfEqual(x, c) => {
+                if self.registers[x as usize] == c.value as u8 {
+                    next_ip += 2;
+                }
+            }
+            Instruction::SkipIfNotEqual(x, c) => {
+                if self.registers[x as usize] != c.value as u8 {
+                    next_ip += 2;
+                }
+            }
+            Instruction::SkipIfRegistersEqual(x, y) => {
+                if self.registers[x as usize] == self.registers[y as usize] {
+                    next_ip += 2;
+                }
+            }
+            Instruction::SetImmediate(x, c) => self.registers[x as usize] = c.value as u8,
+            Instruction::AddImmediate(x, c) => {
+                self.registers[x as usize] = self.registers[x as usize].wrapping_add(c.value as u8)
+            }
+            Instruction::SetRegister(x, y) => self.registers[x as usize] = self.registers[y as usize],
+            Instruction::OrRegister(x, y) => self.registers[x as usize] |= self.registers[y as usize],
+            Instruction::AndRegister(x, y) => self.registers[x as usize] &= self.registers[y as usize],
+            Instruction::XorRegister(x, y) => self.registers[x as usize] ^= self.registers[y as usize],
+            Instruction::AdcRegister(x, y) => {
+                let sum = self.registers[x as usize] as u16 + self.registers[y as usize] as u16;
+                self.registers[x as usize] = sum as u8;
+                self.registers[Register::VF as usize] = if sum > 0xff { 1 } else { 0 };
+            }
+            Instruction::SwbRegister(x, y) => {
+                let (vx, vy) = (self.registers[x as usize], self.registers[y as usize]);
+                self.registers[x as usize] = vx.wrapping_sub(vy);
+                self.registers[Register::VF as usize] = if vx >= vy { 1 } else { 0 };
+            }
+            Instruction::ShrRegister(x, y) => {
+                let src = if quirks.shift_in_place { x } else { y };
+                let value = self.registers[src as usize];
+                self.registers[x as usize] = value >> 1;
+                self.registers[Register::VF as usize] = value & 0x1;
+            }
+            Instruction::ReverseSwbRegister(x, y) => {
+                let (vx, vy) = (self.registers[x as usize], self.registers[y as usize]);
+                self.registers[x as usize] = vy.wrapping_sub(vx);
+                self.registers[Register::VF as usize] = if vy >= vx { 1 } else { 0 };
+            }
+            Instruction::ShlRegister(x, y) => {
+                let src = if quirks.shift_in_place { x } else { y };
+                let value = self.registers[src as usize];
+                self.registers[x as usize] = value << 1;
+                self.registers[Register::VF as usize] = (value & 0x80) >> 7;
+            }
+            Instruction::SkipIfRegistersNotEqual(x, y) => {
+                if self.registers[x as usize] != self.registers[y as usize] {
+                    next_ip += 2;
+                }
+            }
+            Instruction::StoreAddress(addr) => self.addr = addr,
+            Instruction::JumpOffset(addr) => {
+                let offset_register = if quirks.jump_offset_uses_vx {
+                    ((addr.value >> 8) & 0xf) as usize
+                } else {
+                    Register::V0 as usize
+                };
+                next_ip = addr.value as usize + self.registers[offset_register] as usize
+            }
+            Instruction::StoreRandom(x, c) => {
+                self.registers[x as usize] = random_byte() & c.value as u8
+            }
+            Instruction::DrawSprite(x, y, c) => {
+                if quirks.display_wait {
+                    std::thread::sleep(std::time::Duration::from_micros(16_667));
+                }
+                let vx = self.registers[x as usize];
+                let vy = self.registers[y as usize];
+                let height = c.value as usize;
+                let start = (self.addr.value as usize).min(memory.len());
+                let end = (start + height).min(memory.len());
+                let collision = display.draw_sprite(vx, vy, &memory[start..end]);
+                self.registers[Register::VF as usize] = collision as u8;
+            }
+            Instruction::SkipIfPressed(x) => {
+                if keys.is_pressed(self.registers[x as usize]) {
+                    next_ip += 2;
+                }
+            }
+            Instruction::SkipIfNotPressed(x) => {
+                if !keys.is_pressed(self.registers[x as usize]) {
+                    next_ip += 2;
+                }
+            }
+            Instruction::SetFromDelay(x) => self.registers[x as usize] = self.timers.delay as u8,
+            Instruction::WaitKeyPress(x) => match keys.first_pressed() {
+                Some(key) => self.registers[x as usize] = key,
+                None => next_ip = self.ip,
+            },
+            Instruction::SetToDelay(x) => self.timers.delay = self.registers[x as usize] as u16,
+            Instruction::SetToSound(x) => self.timers.sound = self.registers[x as usize] as u16,
+            Instruction::AddAddress(x) => {
+                let sum = self.addr.value as u32 + self.registers[x as usize] as u32;
+                self.addr.value = sum as u16;
+                if quirks.add_address_sets_vf {
+                    self.registers[Register::VF as usize] = if sum > 0x0fff { 1 } else { 0 };
+                }
+            }
+            Instruction::SetAddressToSprite(x) => {
+                self.addr.value = self.registers[x as usize] as u16 * FONT_SPRITE_SIZE
+            }
+            Instruction::StoreBCD(x) => {
+                let value = self.registers[x as usize];
+                let start = self.addr.value as usize;
+                write_byte(memory, start, value / 100);
+                write_byte(memory, start + 1, (value / 10) % 10);
+                write_byte(memory, start + 2, value % 10);
+            }
+            Instruction::StoreRegisters(x) => {
+                let start = self.addr.value as usize;
+                for i in 0..=(x as usize) {
+                    write_byte(memory, start + i, self.registers[i]);
+                }
+                if !quirks.load_store_leaves_i {
+                    self.addr.value += x as u16 + 1;
+                }
+            }
+            Instruction::LoadRegisters(x) => {
+                let start = self.addr.value as usize;
+                for i in 0..=(x as usize) {
+                    self.registers[i] = read_byte(memory, start + i);
+                }
+                if !quirks.load_store_leaves_i {
+                    self.addr.value += x as u16 + 1;
+                }
+            }
         }
+
+        self.finished = next_ip + 1 >= memory.len();
+        self.ip = next_ip;
+    }
+}
+
+/// Reads a byte from `memory`, treating an out-of-range address as zero
+/// rather than panicking — `I` is fully ROM-controlled and can legally end
+/// up past the end of memory.
+fn read_byte(memory: &[u8], addr: usize) -> u8 {
+    memory.get(addr).copied().unwrap_or(0)
+}
+
+/// Writes a byte to `memory`, silently dropping out-of-range addresses.
+fn write_byte(memory: &mut [u8], addr: usize, value: u8) {
+    if let Some(slot) = memory.get_mut(addr) {
+        *slot = value;
     }
 }
 
+fn random_byte() -> u8 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    nanos as u8
+}
+
+/// A single 60Hz tick, the rate both the delay and sound timers count down at.
+const TICK: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Lets the run loop drive a beep while the sound timer is nonzero, without
+/// `Timers` itself depending on any particular audio backend.
+pub trait AudioOutput {
+    fn set_playing(&mut self, playing: bool);
+}
+
+/// An `AudioOutput` that discards the signal, for callers that don't want sound.
+pub struct NullAudioOutput;
+
+impl AudioOutput for NullAudioOutput {
+    fn set_playing(&mut self, _playing: bool) {}
+}
+
 struct Timers {
     delay: u16,
     sound: u16,
@@ -207,22 +543,37 @@ impl Timers {
         }
     }
 
+    /// Decrements `delay`/`sound` by however many whole 60Hz ticks have
+    /// elapsed since the last update, so the timers neither drift against
+    /// the wall clock nor lose ticks when `update` is called infrequently.
     fn update(&mut self) {
         let now = Instant::now();
-        let diff = now.duration_since(self.last_update);
-        if diff.subsec_micros() > 1660 {
-            self.delay = self.delay.saturating_sub(1);
-            self.sound = self.sound.saturating_sub(1);
-            self.last_update = now;
+        let elapsed = now.duration_since(self.last_update);
+        let ticks = (elapsed.as_nanos() / TICK.as_nanos()) as u16;
+        if ticks == 0 {
+            return;
         }
+        self.delay = self.delay.saturating_sub(ticks);
+        self.sound = self.sound.saturating_sub(ticks);
+        self.last_update += TICK * ticks as u32;
+    }
+
+    fn is_sound_active(&self) -> bool {
+        self.sound > 0
     }
 }
 
-fn decode_instruction(state: MachineState, memory: &[u8]) -> Instruction {
-    let a = (memory[state.ip + 0] & 0xf0) >> 4;
-    let b = (memory[state.ip + 0] & 0x0f) >> 0;
-    let c = (memory[state.ip + 1] & 0xf0) >> 4;
-    let d = (memory[state.ip + 1] & 0x0f) >> 0;
+/// Decodes and formats the instruction at `ip`, for disassembly views that
+/// aren't tied to a running `MachineState`.
+pub fn disassemble(ip: usize, memory: &[u8]) -> String {
+    format!("{}", decode_instruction(ip, memory))
+}
+
+fn decode_instruction(ip: usize, memory: &[u8]) -> Instruction {
+    let a = (memory[ip] & 0xf0) >> 4;
+    let b = memory[ip] & 0x0f;
+    let c = (memory[ip + 1] & 0xf0) >> 4;
+    let d = memory[ip + 1] & 0x0f;
     match a {
         0x0 if b == 0x0 && c == 0xe && d == 0x0 => Instruction::ClearScreen,
         0x0 if b == 0x0 && c == 0xe && d == 0xe => Instruction::Return,
@@ -247,11 +598,174 @@ fn decode_instruction(state: MachineState, memory: &[u8]) -> Instruction {
         0xa => Instruction::StoreAddress(Address::new(b, c, d)),
         0xb => Instruction::JumpOffset(Address::new(b, c, d)),
         0xc => Instruction::StoreRandom(Register::new(b), Constant::new(c, d)),
-        0xd => Instruction::DrawSprite(Register::new(b), Register::new(c), Register::new(d)),
+        0xd => Instruction::DrawSprite(Register::new(b), Register::new(c), Constant::new(0, d)),
         0xe if c == 0x9 && d == 0xe => Instruction::SkipIfPressed(Register::new(b)),
-        _ => panic!(format!(
+        0xe if c == 0xa && d == 0x1 => Instruction::SkipIfNotPressed(Register::new(b)),
+        0xf if c == 0x0 && d == 0x7 => Instruction::SetFromDelay(Register::new(b)),
+        0xf if c == 0x0 && d == 0xa => Instruction::WaitKeyPress(Register::new(b)),
+        0xf if c == 0x1 && d == 0x5 => Instruction::SetToDelay(Register::new(b)),
+        0xf if c == 0x1 && d == 0x8 => Instruction::SetToSound(Register::new(b)),
+        0xf if c == 0x1 && d == 0xe => Instruction::AddAddress(Register::new(b)),
+        0xf if c == 0x2 && d == 0x9 => Instruction::SetAddressToSprite(Register::new(b)),
+        0xf if c == 0x3 && d == 0x3 => Instruction::StoreBCD(Register::new(b)),
+        0xf if c == 0x5 && d == 0x5 => Instruction::StoreRegisters(Register::new(b)),
+        0xf if c == 0x6 && d == 0x5 => Instruction::LoadRegisters(Register::new(b)),
+        _ => panic!(
             "Unknown opcode at {:03X}: Instrcu{:02X}{:02X}{:02X}{:02X}",
-            state.ip, a, b, c, d
-        )),
+            ip, a, b, c, d
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(opcodes: &[u8]) -> (MachineState, Vec<u8>, Display) {
+        let mut state = MachineState::new();
+        let mut memory = vec![0u8; 0x1000];
+        memory[0x200..0x200 + opcodes.len()].copy_from_slice(opcodes);
+        let mut display = Display::new();
+        let keys = Keys::new();
+        let quirks = Quirks::chip8();
+        for _ in 0..(opcodes.len() / 2) {
+            state.execute(&mut memory, &mut display, &keys, &quirks, &mut NullAudioOutput);
+        }
+        (state, memory, display)
+    }
+
+    #[test]
+    fn decode_draw_sprite_uses_nibble_height() {
+        let memory = [0xd1, 0x25];
+        match decode_instruction(0, &memory) {
+            Instruction::DrawSprite(_, _, c) => assert_eq!(c.value, 5),
+            other => panic!("expected DrawSprite, got {}", other),
+        }
+    }
+
+    #[test]
+    fn decode_fx_opcodes() {
+        let cases: &[(&[u8], &str)] = &[
+            (&[0xf0, 0x07], "LD V0, DT"),
+            (&[0xf0, 0x0a], "LD V0, K"),
+            (&[0xf0, 0x15], "LD DT, V0"),
+            (&[0xf0, 0x18], "LD ST, V0"),
+            (&[0xf0, 0x1e], "ADD I, V0"),
+            (&[0xf0, 0x29], "LD F, V0"),
+            (&[0xf0, 0x33], "LD B, V0"),
+            (&[0xf0, 0x55], "LD [I], V0"),
+            (&[0xf0, 0x65], "LD V0, [I]"),
+            (&[0xe0, 0xa1], "SKNP V0"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(disassemble(0, bytes), *expected);
+        }
+    }
+
+    #[test]
+    fn adc_sets_vf_on_carry() {
+        let (state, _, _) = run(&[0x60, 0xff, 0x61, 0x01, 0x80, 0x14]);
+        assert_eq!(state.register(0), 0x00);
+        assert_eq!(state.register(0xf), 1);
+    }
+
+    #[test]
+    fn adc_clears_vf_without_carry() {
+        let (state, _, _) = run(&[0x60, 0x01, 0x61, 0x01, 0x80, 0x14]);
+        assert_eq!(state.register(0), 0x02);
+        assert_eq!(state.register(0xf), 0);
+    }
+
+    #[test]
+    fn swb_sets_vf_when_no_borrow() {
+        // V0 -= V1, with V0 >= V1: VF = 1 (not-borrow)
+        let (state, _, _) = run(&[0x60, 0x05, 0x61, 0x03, 0x80, 0x15]);
+        assert_eq!(state.register(0), 2);
+        assert_eq!(state.register(0xf), 1);
+    }
+
+    #[test]
+    fn swb_clears_vf_on_borrow() {
+        let (state, _, _) = run(&[0x60, 0x01, 0x61, 0x03, 0x80, 0x15]);
+        assert_eq!(state.register(0), 0xfe);
+        assert_eq!(state.register(0xf), 0);
+    }
+
+    #[test]
+    fn reverse_swb_sets_vf_when_no_borrow() {
+        // V0 = V1 - V0, with V1 >= V0: VF = 1
+        let (state, _, _) = run(&[0x60, 0x02, 0x61, 0x05, 0x80, 0x17]);
+        assert_eq!(state.register(0), 3);
+        assert_eq!(state.register(0xf), 1);
+    }
+
+    #[test]
+    fn reverse_swb_clears_vf_on_borrow() {
+        let (state, _, _) = run(&[0x60, 0x05, 0x61, 0x02, 0x80, 0x17]);
+        assert_eq!(state.register(0), 0xfd);
+        assert_eq!(state.register(0xf), 0);
+    }
+
+    #[test]
+    fn store_bcd_splits_into_digits() {
+        let (_, memory, _) = run(&[0x60, 195, 0xa3, 0x00, 0xf0, 0x33]);
+        assert_eq!(&memory[0x300..0x303], &[1, 9, 5]);
+    }
+
+    #[test]
+    fn store_and_load_registers_round_trip() {
+        let mut state = MachineState::new();
+        let mut memory = vec![0u8; 0x1000];
+        memory[0x200] = 0xa3; // LD I, 0x300
+        memory[0x201] = 0x00;
+        memory[0x202] = 0xf2; // LD [I], V2
+        memory[0x203] = 0x55;
+        memory[0x204] = 0xa3; // LD I, 0x300
+        memory[0x205] = 0x00;
+        memory[0x206] = 0xf2; // LD V2, [I]
+        memory[0x207] = 0x65;
+        let mut display = Display::new();
+        let keys = Keys::new();
+        let quirks = Quirks::chip8();
+        state.set_register(0, 0x11);
+        state.set_register(1, 0x22);
+        state.set_register(2, 0x33);
+        for _ in 0..4 {
+            state.execute(&mut memory, &mut display, &keys, &quirks, &mut NullAudioOutput);
+        }
+        assert_eq!(state.register(0), 0x11);
+        assert_eq!(state.register(1), 0x22);
+        assert_eq!(state.register(2), 0x33);
+    }
+
+    #[test]
+    fn draw_sprite_detects_collision_and_wraps() {
+        let mut state = MachineState::new();
+        let mut memory = vec![0u8; 0x1000];
+        // sprite data: single row, all 8 pixels set
+        memory[0x300] = 0xff;
+        memory[0x200] = 0xa3; // LD I, 0x300
+        memory[0x201] = 0x00;
+        memory[0x202] = 0x60; // LD V0, 63 (draw x wraps: column 63 on screen, columns 0-6 wrapped)
+        memory[0x203] = 63;
+        memory[0x204] = 0x61; // LD V1, 0
+        memory[0x205] = 0x00;
+        memory[0x206] = 0xd0; // DRW V0, V1, 1
+        memory[0x207] = 0x11;
+        memory[0x208] = 0xd0; // DRW V0, V1, 1 (draws over itself -> collision)
+        memory[0x209] = 0x11;
+        let mut display = Display::new();
+        let keys = Keys::new();
+        let quirks = Quirks::chip8();
+        for _ in 0..4 {
+            state.execute(&mut memory, &mut display, &keys, &quirks, &mut NullAudioOutput);
+        }
+        assert_eq!(state.register(0xf), 0); // first draw: no collision yet
+        assert!(display.pixel(63, 0));
+        assert!(display.pixel(0, 0)); // wrapped around to column 0
+
+        state.execute(&mut memory, &mut display, &keys, &quirks, &mut NullAudioOutput);
+        assert_eq!(state.register(0xf), 1); // second draw collides with the first
+        assert!(!display.pixel(63, 0)); // XORed back off
     }
 }
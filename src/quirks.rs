@@ -0,0 +1,172 @@
+/// Several CHIP-8 opcodes are ambiguous across interpreter lineages and
+/// hard-coding one interpretation breaks ROMs written for another. `Quirks`
+/// is threaded through `MachineState::execute` so callers can pick the
+/// dialect a ROM expects.
+pub struct Quirks {
+    /// `8xy6`/`8xyE`: shift `Vx` in place, rather than copying `Vy` into
+    /// `Vx` first and shifting the result.
+    pub shift_in_place: bool,
+    /// `Fx55`/`Fx65`: leave `I` unchanged, rather than incrementing it by
+    /// `x + 1` after the store/load.
+    pub load_store_leaves_i: bool,
+    /// `Bnnn`: add `Vx` (the high nibble of the address) instead of `V0`.
+    pub jump_offset_uses_vx: bool,
+    /// `Fx1E`: set `VF` when adding to `I` overflows past `0x0FFF`.
+    pub add_address_sets_vf: bool,
+    /// `Dxyn`: block until the next 60Hz tick before drawing.
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    /// The original COSMAC VIP CHIP-8 behavior.
+    pub fn chip8() -> Quirks {
+        Quirks {
+            shift_in_place: false,
+            load_store_leaves_i: false,
+            jump_offset_uses_vx: false,
+            add_address_sets_vf: false,
+            display_wait: true,
+        }
+    }
+
+    /// SUPER-CHIP, as implemented on the HP48 calculators.
+    pub fn schip() -> Quirks {
+        Quirks {
+            shift_in_place: true,
+            load_store_leaves_i: true,
+            jump_offset_uses_vx: true,
+            add_address_sets_vf: false,
+            display_wait: false,
+        }
+    }
+
+    /// XO-CHIP, the modern extended dialect.
+    pub fn xochip() -> Quirks {
+        Quirks {
+            shift_in_place: true,
+            load_store_leaves_i: false,
+            jump_offset_uses_vx: false,
+            add_address_sets_vf: true,
+            display_wait: false,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks::chip8()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Display, Keys, MachineState, NullAudioOutput};
+    use std::time::{Duration, Instant};
+
+    fn new_machine() -> (MachineState, Vec<u8>, Display, Keys) {
+        (MachineState::new(), vec![0u8; 0x400], Display::new(), Keys::new())
+    }
+
+    fn shr_result(quirks: &Quirks) -> (u8, u8) {
+        let (mut state, mut memory, mut display, keys) = new_machine();
+        memory[0x200] = 0x86; // 8xy6, x=6
+        memory[0x201] = 0x16; // y=1
+        state.set_register(6, 0b0000_0010); // Vx
+        state.set_register(1, 0b0000_0011); // Vy
+        state.execute(&mut memory, &mut display, &keys, quirks, &mut NullAudioOutput);
+        (state.register(6), state.register(0xf))
+    }
+
+    #[test]
+    fn chip8_shr_shifts_vy_into_vx() {
+        assert_eq!(shr_result(&Quirks::chip8()), (0b0000_0001, 1));
+    }
+
+    #[test]
+    fn schip_shr_shifts_vx_in_place() {
+        assert_eq!(shr_result(&Quirks::schip()), (0b0000_0001, 0));
+    }
+
+    fn store_registers_address(quirks: &Quirks) -> u16 {
+        let (mut state, mut memory, mut display, keys) = new_machine();
+        memory[0x200] = 0xa3; // LD I, 0x300
+        memory[0x201] = 0x00;
+        memory[0x202] = 0xf2; // LD [I], V2
+        memory[0x203] = 0x55;
+        state.execute(&mut memory, &mut display, &keys, quirks, &mut NullAudioOutput);
+        state.execute(&mut memory, &mut display, &keys, quirks, &mut NullAudioOutput);
+        state.address()
+    }
+
+    #[test]
+    fn chip8_store_registers_increments_i() {
+        assert_eq!(store_registers_address(&Quirks::chip8()), 0x303);
+    }
+
+    #[test]
+    fn schip_store_registers_leaves_i_unchanged() {
+        assert_eq!(store_registers_address(&Quirks::schip()), 0x300);
+    }
+
+    fn jump_offset_target(quirks: &Quirks) -> usize {
+        let (mut state, mut memory, mut display, keys) = new_machine();
+        memory[0x200] = 0xb3; // JP V0, 0x345 (Bnnn, high address nibble 3)
+        memory[0x201] = 0x45;
+        state.set_register(0, 0x10);
+        state.set_register(3, 0x20);
+        state.execute(&mut memory, &mut display, &keys, quirks, &mut NullAudioOutput);
+        state.ip()
+    }
+
+    #[test]
+    fn chip8_jump_offset_adds_v0() {
+        assert_eq!(jump_offset_target(&Quirks::chip8()), 0x355);
+    }
+
+    #[test]
+    fn schip_jump_offset_adds_vx() {
+        assert_eq!(jump_offset_target(&Quirks::schip()), 0x365);
+    }
+
+    fn add_address_vf(quirks: &Quirks) -> u8 {
+        let (mut state, mut memory, mut display, keys) = new_machine();
+        memory[0x200] = 0xaf; // LD I, 0xFFF
+        memory[0x201] = 0xff;
+        memory[0x202] = 0xf0; // ADD I, V0
+        memory[0x203] = 0x1e;
+        state.set_register(0, 1);
+        state.execute(&mut memory, &mut display, &keys, quirks, &mut NullAudioOutput);
+        state.execute(&mut memory, &mut display, &keys, quirks, &mut NullAudioOutput);
+        state.register(0xf)
+    }
+
+    #[test]
+    fn chip8_add_address_does_not_set_vf_on_overflow() {
+        assert_eq!(add_address_vf(&Quirks::chip8()), 0);
+    }
+
+    #[test]
+    fn xochip_add_address_sets_vf_on_overflow() {
+        assert_eq!(add_address_vf(&Quirks::xochip()), 1);
+    }
+
+    fn draw_sprite_elapsed(quirks: &Quirks) -> Duration {
+        let (mut state, mut memory, mut display, keys) = new_machine();
+        memory[0x200] = 0xd0; // DRW V0, V0, 1
+        memory[0x201] = 0x01;
+        let start = Instant::now();
+        state.execute(&mut memory, &mut display, &keys, quirks, &mut NullAudioOutput);
+        start.elapsed()
+    }
+
+    #[test]
+    fn chip8_draw_sprite_waits_for_the_next_tick() {
+        assert!(draw_sprite_elapsed(&Quirks::chip8()) >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn schip_draw_sprite_does_not_wait() {
+        assert!(draw_sprite_elapsed(&Quirks::schip()) < Duration::from_millis(10));
+    }
+}